@@ -14,6 +14,13 @@
 //! Whenever a borrow error happens the current
 //! locations of where known borrows were created will be printed out as well.
 //!
+//! # Crate features
+//!
+//! * `debug_refcell` - Keeps the borrow-location tracking enabled even in
+//!   release builds (it's otherwise gated on `cfg(debug_assertions)`). Turn
+//!   this on if you need actionable borrow-panic diagnostics from optimized
+//!   builds running in the field.
+//!
 //! # Examples
 //!
 //! ```no_run
@@ -31,7 +38,7 @@
 
 /// Error kind ported from nightly std
 pub mod error {
-    #[cfg(debug_assertions)]
+    #[cfg(any(debug_assertions, feature = "debug_refcell"))]
     fn locations_display(locations: &[super::Location]) -> String {
         locations
             .iter()
@@ -44,10 +51,10 @@ pub mod error {
     #[derive(Debug)]
     pub struct BorrowError {
         /// Debug-only location of attempted borrow
-        #[cfg(debug_assertions)]
+        #[cfg(any(debug_assertions, feature = "debug_refcell"))]
         pub attempted_at: super::Location,
         /// Debug-only location of all current locations
-        #[cfg(debug_assertions)]
+        #[cfg(any(debug_assertions, feature = "debug_refcell"))]
         pub already_borrowed_at: Vec<super::Location>,
     }
 
@@ -55,7 +62,7 @@ pub mod error {
 
     impl std::fmt::Display for BorrowError {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            #[cfg(debug_assertions)]
+            #[cfg(any(debug_assertions, feature = "debug_refcell"))]
             {
                 write!(
                     f,
@@ -63,7 +70,7 @@ pub mod error {
                     locations_display(&self.already_borrowed_at)
                 )
             }
-            #[cfg(not(debug_assertions))]
+            #[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
             {
                 write!(f, "Value is already borrowed mutably")
             }
@@ -77,16 +84,16 @@ pub mod error {
     #[non_exhaustive]
     pub struct BorrowMutError {
         /// Debug-only location of attempted borrow
-        #[cfg(debug_assertions)]
+        #[cfg(any(debug_assertions, feature = "debug_refcell"))]
         pub attempted_at: super::Location,
         /// Debug-only locations of all current borrows
-        #[cfg(debug_assertions)]
+        #[cfg(any(debug_assertions, feature = "debug_refcell"))]
         pub already_borrowed_at: Vec<super::Location>,
     }
 
     impl std::fmt::Display for BorrowMutError {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-            #[cfg(debug_assertions)]
+            #[cfg(any(debug_assertions, feature = "debug_refcell"))]
             {
                 write!(
                     f,
@@ -94,15 +101,77 @@ pub mod error {
                     locations_display(&self.already_borrowed_at)
                 )
             }
-            #[cfg(not(debug_assertions))]
+            #[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
             {
                 write!(f, "Value is already borrowed")
             }
         }
     }
+
+    impl std::error::Error for RwLockReadError {}
+
+    /// An error returned by [`RwLock::try_read`].
+    #[non_exhaustive]
+    #[derive(Debug)]
+    pub struct RwLockReadError {
+        /// Debug-only location of attempted lock
+        #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+        pub attempted_at: super::Location,
+        /// Debug-only location of the writer currently holding the lock
+        #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+        pub already_locked_at: Vec<super::Location>,
+    }
+
+    impl std::fmt::Display for RwLockReadError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+            {
+                write!(
+                    f,
+                    "Value is already locked for writing, current active lock: \n{}\n\n",
+                    locations_display(&self.already_locked_at)
+                )
+            }
+            #[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
+            {
+                write!(f, "Value is already locked for writing")
+            }
+        }
+    }
+
+    impl std::error::Error for RwLockWriteError {}
+
+    /// An error returned by [`RwLock::try_write`].
+    #[non_exhaustive]
+    #[derive(Debug)]
+    pub struct RwLockWriteError {
+        /// Debug-only location of attempted lock
+        #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+        pub attempted_at: super::Location,
+        /// Debug-only locations of all current readers, or the current writer
+        #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+        pub already_locked_at: Vec<super::Location>,
+    }
+
+    impl std::fmt::Display for RwLockWriteError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+            {
+                write!(
+                    f,
+                    "Value is already locked, current active locks:\n{}\n\n",
+                    locations_display(&self.already_locked_at)
+                )
+            }
+            #[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
+            {
+                write!(f, "Value is already locked")
+            }
+        }
+    }
 }
 
-#[cfg(debug_assertions)]
+#[cfg(any(debug_assertions, feature = "debug_refcell"))]
 use std::cell::RefCell as StdRefCell;
 use std::cell::{Cell, UnsafeCell};
 use std::ops::{Deref, DerefMut};
@@ -113,10 +182,10 @@ pub struct RefCell<T: ?Sized> {
     value: UnsafeCell<T>,
 }
 
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
 type Location = ();
 
-#[cfg(debug_assertions)]
+#[cfg(any(debug_assertions, feature = "debug_refcell"))]
 type Location = &'static std::panic::Location<'static>;
 
 /// An enumeration of values returned from the `state` method on a `RefCell<T>`.
@@ -135,12 +204,18 @@ pub enum BorrowState {
 struct BorrowFlag {
     flag: Cell<usize>,
 
-    #[cfg(debug_assertions)]
+    #[cfg(any(debug_assertions, feature = "debug_refcell"))]
     locations: StdRefCell<Vec<Location>>,
 }
 
 const UNUSED: usize = 0;
-const WRITING: usize = !0;
+
+// Reads live in `[1, MIN_WRITING - 1]` and writes live in
+// `[MIN_WRITING, usize::MAX]`. This (instead of a single `WRITING` sentinel)
+// is what lets a mutable borrow be split into more than one `RefMut` via
+// `RefMut::map_split`: each split borrow just bumps the write count, the same
+// way each additional `Ref` bumps the read count.
+const MIN_WRITING: usize = !0 / 2 + 1;
 
 impl<T> RefCell<T> {
     /// Creates a new `RefCell` containing `value`.
@@ -152,7 +227,7 @@ impl<T> RefCell<T> {
     }
 
     /// Consumes the `RefCell`, returning the wrapped value.
-    #[cfg_attr(debug_assertions, track_caller)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
     pub fn into_inner(self) -> T {
         debug_assert!(self.borrow.flag.get() == UNUSED);
         self.value.into_inner()
@@ -168,8 +243,8 @@ impl<T: ?Sized> RefCell<T> {
     /// # Panics
     ///
     /// Panics if the value is currently mutably borrowed.
-    #[cfg_attr(debug_assertions, inline(never))]
-    #[cfg_attr(debug_assertions, track_caller)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
     pub fn borrow(&self) -> Ref<'_, T> {
         match self.try_borrow() {
             Ok(value) => value,
@@ -188,8 +263,8 @@ impl<T: ?Sized> RefCell<T> {
     /// # Panics
     ///
     /// Panics if the value is currently mutably borrowed.
-    #[cfg_attr(debug_assertions, inline(never))]
-    #[cfg_attr(debug_assertions, track_caller)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
     pub fn try_borrow(&self) -> Result<Ref<'_, T>, crate::error::BorrowError> {
         match BorrowRef::new(&self.borrow) {
             Some(b) => Ok(Ref {
@@ -197,14 +272,14 @@ impl<T: ?Sized> RefCell<T> {
                 _borrow: b,
             }),
             None => {
-                #[cfg(debug_assertions)]
+                #[cfg(any(debug_assertions, feature = "debug_refcell"))]
                 {
                     Err(crate::error::BorrowError {
                         attempted_at: get_caller(),
                         already_borrowed_at: self.borrow.locations.borrow().clone(),
                     })
                 }
-                #[cfg(not(debug_assertions))]
+                #[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
                 {
                     Err(crate::error::BorrowError {})
                 }
@@ -220,8 +295,8 @@ impl<T: ?Sized> RefCell<T> {
     /// # Panics
     ///
     /// Panics if the value is currently borrowed.
-    #[cfg_attr(debug_assertions, inline(never))]
-    #[cfg_attr(debug_assertions, track_caller)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
     pub fn borrow_mut(&self) -> RefMut<'_, T> {
         match self.try_borrow_mut() {
             Ok(value) => value,
@@ -238,8 +313,8 @@ impl<T: ?Sized> RefCell<T> {
     /// The borrow lasts until the returned `RefMut` exits scope. The value
     /// cannot be borrowed while this borrow is active.
     ///
-    #[cfg_attr(debug_assertions, inline(never))]
-    #[cfg_attr(debug_assertions, track_caller)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
     pub fn try_borrow_mut(&self) -> Result<RefMut<'_, T>, error::BorrowMutError> {
         match BorrowRefMut::new(&self.borrow) {
             Some(b) => Ok(RefMut {
@@ -247,23 +322,37 @@ impl<T: ?Sized> RefCell<T> {
                 _borrow: b,
             }),
             None => {
-                #[cfg(debug_assertions)]
+                #[cfg(any(debug_assertions, feature = "debug_refcell"))]
                 {
                     Err(error::BorrowMutError {
                         attempted_at: get_caller(),
                         already_borrowed_at: self.borrow.locations.borrow().clone(),
                     })
                 }
-                #[cfg(not(debug_assertions))]
+                #[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
                 {
                     Err(error::BorrowMutError {})
                 }
             }
         }
     }
+
+    /// Returns the current borrow state of this `RefCell`.
+    ///
+    /// This allows callers to check whether the cell is currently borrowed,
+    /// and if so whether the borrow is shared or exclusive, without going
+    /// through the `Result`/`Err` path of `try_borrow`.
+    #[inline]
+    pub fn state(&self) -> BorrowState {
+        match self.borrow.flag.get() {
+            UNUSED => BorrowState::Unused,
+            n if n >= MIN_WRITING => BorrowState::Writing,
+            _ => BorrowState::Reading,
+        }
+    }
 }
 
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
 impl BorrowFlag {
     #[inline]
     fn new() -> BorrowFlag {
@@ -279,7 +368,7 @@ impl BorrowFlag {
     fn pop(&self) {}
 }
 
-#[cfg(debug_assertions)]
+#[cfg(any(debug_assertions, feature = "debug_refcell"))]
 impl BorrowFlag {
     fn new() -> BorrowFlag {
         BorrowFlag {
@@ -297,11 +386,11 @@ impl BorrowFlag {
     }
 }
 
-#[cfg(not(debug_assertions))]
+#[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
 #[inline]
 fn get_caller() -> Location {}
 
-#[cfg(debug_assertions)]
+#[cfg(any(debug_assertions, feature = "debug_refcell"))]
 #[inline(never)]
 #[track_caller]
 fn get_caller() -> Location {
@@ -338,25 +427,45 @@ struct BorrowRef<'b> {
 }
 
 impl<'b> BorrowRef<'b> {
-    #[cfg_attr(not(debug_assertions), inline)]
-    #[cfg_attr(debug_assertions, inline(never))]
-    #[cfg_attr(debug_assertions, track_caller)]
+    #[cfg_attr(not(any(debug_assertions, feature = "debug_refcell")), inline)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
     fn new(borrow: &'b BorrowFlag) -> Option<BorrowRef<'b>> {
         let flag = borrow.flag.get();
-        if flag == WRITING {
+        if flag >= MIN_WRITING {
             return None;
         }
-        borrow.flag.set(flag + 1);
+        let flag = flag + 1;
+        assert!(flag < MIN_WRITING, "too many immutable borrows");
+        borrow.flag.set(flag);
         borrow.push(get_caller());
         Some(BorrowRef { borrow })
     }
+
+    /// Clones this borrow, adding another active read to the same
+    /// `BorrowFlag`. Used by `Ref::map_split` to let two `Ref`s share the
+    /// one borrow that was already taken out.
+    #[cfg_attr(not(any(debug_assertions, feature = "debug_refcell")), inline)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    fn clone(&self) -> BorrowRef<'b> {
+        let flag = self.borrow.flag.get();
+        debug_assert!(flag > UNUSED && flag < MIN_WRITING);
+        let flag = flag + 1;
+        assert!(flag < MIN_WRITING, "too many immutable borrows");
+        self.borrow.flag.set(flag);
+        self.borrow.push(get_caller());
+        BorrowRef {
+            borrow: self.borrow,
+        }
+    }
 }
 
 impl<'b> Drop for BorrowRef<'b> {
     #[inline]
     fn drop(&mut self) {
         let flag = self.borrow.flag.get();
-        debug_assert!(flag != WRITING && flag != UNUSED);
+        debug_assert!(flag > UNUSED && flag < MIN_WRITING);
         self.borrow.flag.set(flag - 1);
         self.borrow.pop();
     }
@@ -403,6 +512,46 @@ impl<'b, T: ?Sized + 'b> Ref<'b, T> {
             _borrow: orig._borrow,
         }
     }
+
+    /// Splits a `Ref` into two `Ref`s for different components of the
+    /// borrowed data.
+    ///
+    /// The `RefCell` is already immutably borrowed, so this cannot fail.
+    ///
+    /// This is an associated function that needs to be used as
+    /// `Ref::map_split(...)`. A method would interfere with methods of the
+    /// same name on the contents of a `RefCell` used through `Deref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::cell::{RefCell, Ref};
+    ///
+    /// let c = RefCell::new((1, 'b'));
+    /// let b1: Ref<'_, (u32, char)> = c.borrow();
+    /// let (b2, b3) = Ref::map_split(b1, |t| (&t.0, &t.1));
+    /// assert_eq!(*b2, 1);
+    /// assert_eq!(*b3, 'b');
+    /// ```
+    #[inline]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    pub fn map_split<U: ?Sized, V: ?Sized, F>(orig: Ref<'b, T>, f: F) -> (Ref<'b, U>, Ref<'b, V>)
+    where
+        F: FnOnce(&T) -> (&U, &V),
+    {
+        let (a, b) = f(orig._value);
+        let borrow = orig._borrow.clone();
+        (
+            Ref {
+                _value: a,
+                _borrow: borrow,
+            },
+            Ref {
+                _value: b,
+                _borrow: orig._borrow,
+            },
+        )
+    }
 }
 
 impl<'b, T: ?Sized> Deref for Ref<'b, T> {
@@ -417,24 +566,46 @@ struct BorrowRefMut<'b> {
 }
 
 impl<'b> BorrowRefMut<'b> {
-    #[cfg_attr(not(debug_assertions), inline)]
-    #[cfg_attr(debug_assertions, inline(never))]
-    #[cfg_attr(debug_assertions, track_caller)]
+    #[cfg_attr(not(any(debug_assertions, feature = "debug_refcell")), inline)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
     fn new(borrow: &'b BorrowFlag) -> Option<BorrowRefMut<'b>> {
         if borrow.flag.get() != UNUSED {
             return None;
         }
-        borrow.flag.set(WRITING);
+        borrow.flag.set(MIN_WRITING);
         borrow.push(get_caller());
         Some(BorrowRefMut { borrow })
     }
+
+    /// Clones this borrow, adding another active write to the same
+    /// `BorrowFlag`. Used by `RefMut::map_split` to let two `RefMut`s share
+    /// the one borrow that was already taken out.
+    #[cfg_attr(not(any(debug_assertions, feature = "debug_refcell")), inline)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    fn clone(&self) -> BorrowRefMut<'b> {
+        let flag = self.borrow.flag.get();
+        debug_assert!(flag >= MIN_WRITING);
+        let flag = flag.checked_add(1).expect("too many mutable borrows");
+        self.borrow.flag.set(flag);
+        self.borrow.push(get_caller());
+        BorrowRefMut {
+            borrow: self.borrow,
+        }
+    }
 }
 
 impl<'b> Drop for BorrowRefMut<'b> {
     #[inline]
     fn drop(&mut self) {
-        debug_assert!(self.borrow.flag.get() == WRITING);
-        self.borrow.flag.set(UNUSED);
+        let flag = self.borrow.flag.get();
+        debug_assert!(flag >= MIN_WRITING);
+        // The last split-off write drops the flag straight back to `UNUSED`
+        // rather than down into the read range.
+        self.borrow
+            .flag
+            .set(if flag == MIN_WRITING { UNUSED } else { flag - 1 });
         self.borrow.pop();
     }
 }
@@ -480,6 +651,53 @@ impl<'b, T: ?Sized + 'b> RefMut<'b, T> {
             _borrow: orig._borrow,
         }
     }
+
+    /// Splits a `RefMut` into two `RefMut`s for different components of the
+    /// borrowed data.
+    ///
+    /// The `RefCell` is already mutably borrowed, so this cannot fail.
+    ///
+    /// This is an associated function that needs to be used as
+    /// `RefMut::map_split(...)`. A method would interfere with methods of
+    /// the same name on the contents of a `RefCell` used through `Deref`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use std::cell::{RefCell, RefMut};
+    ///
+    /// let c = RefCell::new([1, 2, 3, 4]);
+    /// let b1: RefMut<'_, [u32; 4]> = c.borrow_mut();
+    /// let (mut b2, mut b3) = RefMut::map_split(b1, |t| t.split_at_mut(2));
+    /// assert_eq!(*b2, [1, 2]);
+    /// assert_eq!(*b3, [3, 4]);
+    /// b2[0] = 42;
+    /// b3[0] = 24;
+    /// drop((b2, b3));
+    /// assert_eq!(*c.borrow(), [42, 2, 24, 4]);
+    /// ```
+    #[inline]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    pub fn map_split<U: ?Sized, V: ?Sized, F>(
+        orig: RefMut<'b, T>,
+        f: F,
+    ) -> (RefMut<'b, U>, RefMut<'b, V>)
+    where
+        F: FnOnce(&mut T) -> (&mut U, &mut V),
+    {
+        let (a, b) = f(orig._value);
+        let borrow = orig._borrow.clone();
+        (
+            RefMut {
+                _value: a,
+                _borrow: borrow,
+            },
+            RefMut {
+                _value: b,
+                _borrow: orig._borrow,
+            },
+        )
+    }
 }
 
 impl<'b, T: ?Sized> Deref for RefMut<'b, T> {
@@ -495,6 +713,307 @@ impl<'b, T: ?Sized> DerefMut for RefMut<'b, T> {
     }
 }
 
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(any(debug_assertions, feature = "debug_refcell"))]
+use std::sync::Mutex as StdMutex;
+
+const RWLOCK_UNUSED: usize = 0;
+const RWLOCK_WRITING: usize = !0;
+
+/// A thread-safe clone of the standard library's `RwLock` type with the same
+/// debug-only borrow-location diagnostics that `RefCell` provides.
+///
+/// Unlike `std::sync::RwLock`, a lock conflict is treated the same way a
+/// `RefCell` borrow conflict is: `read`/`write` panic immediately (rather
+/// than blocking) and, in debug builds, the panic message lists the
+/// locations of every lock currently held, exactly as `RefCell`'s
+/// `BorrowError`/`BorrowMutError` do.
+///
+/// # Examples
+///
+/// ```no_run
+/// use debug_cell::RwLock;
+///
+/// let r = RwLock::new(3);
+/// let a = r.read();
+///
+/// // This panics because the lock is already held for reading above; in
+/// // debug builds the panic message also prints where that read lock was
+/// // taken.
+/// let b = r.write();
+/// ```
+pub struct RwLock<T: ?Sized> {
+    borrow: RwLockFlag,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+struct RwLockFlag {
+    flag: AtomicUsize,
+
+    #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+    locations: StdMutex<Vec<Location>>,
+}
+
+#[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
+impl RwLockFlag {
+    #[inline]
+    fn new() -> RwLockFlag {
+        RwLockFlag {
+            flag: AtomicUsize::new(RWLOCK_UNUSED),
+        }
+    }
+
+    #[inline]
+    fn push(&self, _caller: Location) {}
+
+    #[inline]
+    fn pop(&self) {}
+}
+
+#[cfg(any(debug_assertions, feature = "debug_refcell"))]
+impl RwLockFlag {
+    fn new() -> RwLockFlag {
+        RwLockFlag {
+            flag: AtomicUsize::new(RWLOCK_UNUSED),
+            locations: StdMutex::new(Vec::new()),
+        }
+    }
+
+    fn push(&self, caller: Location) {
+        self.locations.lock().unwrap().push(caller);
+    }
+
+    fn pop(&self) {
+        self.locations.lock().unwrap().pop();
+    }
+
+    fn locations(&self) -> Vec<Location> {
+        self.locations.lock().unwrap().clone()
+    }
+}
+
+impl<T> RwLock<T> {
+    /// Creates a new `RwLock` containing `value`.
+    pub fn new(value: T) -> RwLock<T> {
+        RwLock {
+            borrow: RwLockFlag::new(),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Consumes the `RwLock`, returning the wrapped value.
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    pub fn into_inner(self) -> T {
+        debug_assert!(self.borrow.flag.load(Ordering::Acquire) == RWLOCK_UNUSED);
+        self.value.into_inner()
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    /// Locks this `RwLock` with shared read access.
+    ///
+    /// The lock lasts until the returned `RwLockReadGuard` exits scope.
+    /// Multiple read locks can be held at the same time.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently write-locked.
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    pub fn read(&self) -> RwLockReadGuard<'_, T> {
+        match self.try_read() {
+            Ok(value) => value,
+            Err(message) => panic!(
+                "Read-locking {} failed: {}",
+                std::any::type_name::<Self>(),
+                message
+            ),
+        }
+    }
+
+    /// Attempts to lock this `RwLock` with shared read access.
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    pub fn try_read(&self) -> Result<RwLockReadGuard<'_, T>, error::RwLockReadError> {
+        match RwBorrowRef::new(&self.borrow) {
+            Some(b) => Ok(RwLockReadGuard {
+                _value: unsafe { &*self.value.get() },
+                _borrow: b,
+            }),
+            None => {
+                #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+                {
+                    Err(error::RwLockReadError {
+                        attempted_at: get_caller(),
+                        already_locked_at: self.borrow.locations(),
+                    })
+                }
+                #[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
+                {
+                    Err(error::RwLockReadError {})
+                }
+            }
+        }
+    }
+
+    /// Locks this `RwLock` with exclusive write access.
+    ///
+    /// The lock lasts until the returned `RwLockWriteGuard` exits scope. The
+    /// value cannot be locked again while this lock is active.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the value is currently locked, for reading or writing.
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    pub fn write(&self) -> RwLockWriteGuard<'_, T> {
+        match self.try_write() {
+            Ok(value) => value,
+            Err(message) => panic!(
+                "Write-locking {} failed: {}",
+                std::any::type_name::<Self>(),
+                message
+            ),
+        }
+    }
+
+    /// Attempts to lock this `RwLock` with exclusive write access.
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    pub fn try_write(&self) -> Result<RwLockWriteGuard<'_, T>, error::RwLockWriteError> {
+        match RwBorrowRefMut::new(&self.borrow) {
+            Some(b) => Ok(RwLockWriteGuard {
+                _value: unsafe { &mut *self.value.get() },
+                _borrow: b,
+            }),
+            None => {
+                #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+                {
+                    Err(error::RwLockWriteError {
+                        attempted_at: get_caller(),
+                        already_locked_at: self.borrow.locations(),
+                    })
+                }
+                #[cfg(not(any(debug_assertions, feature = "debug_refcell")))]
+                {
+                    Err(error::RwLockWriteError {})
+                }
+            }
+        }
+    }
+}
+
+impl<T: Default> Default for RwLock<T> {
+    #[inline]
+    fn default() -> RwLock<T> {
+        RwLock::new(Default::default())
+    }
+}
+
+struct RwBorrowRef<'b> {
+    borrow: &'b RwLockFlag,
+}
+
+impl<'b> RwBorrowRef<'b> {
+    #[cfg_attr(not(any(debug_assertions, feature = "debug_refcell")), inline)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    fn new(borrow: &'b RwLockFlag) -> Option<RwBorrowRef<'b>> {
+        loop {
+            let flag = borrow.flag.load(Ordering::Acquire);
+            if flag == RWLOCK_WRITING {
+                return None;
+            }
+            let next = flag + 1;
+            assert!(next != RWLOCK_WRITING, "too many readers");
+            if borrow
+                .flag
+                .compare_exchange_weak(flag, next, Ordering::AcqRel, Ordering::Acquire)
+                .is_ok()
+            {
+                borrow.push(get_caller());
+                return Some(RwBorrowRef { borrow });
+            }
+        }
+    }
+}
+
+impl<'b> Drop for RwBorrowRef<'b> {
+    #[inline]
+    fn drop(&mut self) {
+        self.borrow.pop();
+        self.borrow.flag.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+struct RwBorrowRefMut<'b> {
+    borrow: &'b RwLockFlag,
+}
+
+impl<'b> RwBorrowRefMut<'b> {
+    #[cfg_attr(not(any(debug_assertions, feature = "debug_refcell")), inline)]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), inline(never))]
+    #[cfg_attr(any(debug_assertions, feature = "debug_refcell"), track_caller)]
+    fn new(borrow: &'b RwLockFlag) -> Option<RwBorrowRefMut<'b>> {
+        borrow
+            .flag
+            .compare_exchange(
+                RWLOCK_UNUSED,
+                RWLOCK_WRITING,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            )
+            .ok()?;
+        borrow.push(get_caller());
+        Some(RwBorrowRefMut { borrow })
+    }
+}
+
+impl<'b> Drop for RwBorrowRefMut<'b> {
+    #[inline]
+    fn drop(&mut self) {
+        self.borrow.pop();
+        self.borrow.flag.store(RWLOCK_UNUSED, Ordering::Release);
+    }
+}
+
+/// A wrapper type for a shared, read-locked reference to a value in a
+/// `RwLock<T>`.
+pub struct RwLockReadGuard<'b, T: ?Sized + 'b> {
+    _value: &'b T,
+    _borrow: RwBorrowRef<'b>,
+}
+
+impl<'b, T: ?Sized> Deref for RwLockReadGuard<'b, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self._value
+    }
+}
+
+/// A wrapper type for an exclusive, write-locked reference to a value in a
+/// `RwLock<T>`.
+pub struct RwLockWriteGuard<'b, T: ?Sized + 'b> {
+    _value: &'b mut T,
+    _borrow: RwBorrowRefMut<'b>,
+}
+
+impl<'b, T: ?Sized> Deref for RwLockWriteGuard<'b, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        self._value
+    }
+}
+
+impl<'b, T: ?Sized> DerefMut for RwLockWriteGuard<'b, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self._value
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::RefCell;
@@ -562,4 +1081,144 @@ mod tests {
         let _a = a.borrow_mut();
         a.borrow();
     }
+
+    // Exercises the location tracking that `debug_refcell` is meant to keep
+    // alive outside of `debug_assertions` builds too.
+    #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+    #[test]
+    fn debug_refcell_location_tracking() {
+        let a = RefCell::new(2);
+        let _guard = a.borrow();
+        let err = match a.try_borrow_mut() {
+            Ok(_) => panic!("expected a borrow conflict"),
+            Err(err) => err,
+        };
+        assert_eq!(err.already_borrowed_at.len(), 1);
+        assert!(format!("{err}").contains("lib.rs"));
+    }
+
+    #[test]
+    fn ref_map_split() {
+        let a = RefCell::new((1, 'b'));
+        let b = a.borrow();
+        let (c, d) = super::Ref::map_split(b, |t| (&t.0, &t.1));
+        assert_eq!(*c, 1);
+        assert_eq!(*d, 'b');
+        assert!(a.try_borrow().is_ok());
+        assert!(a.try_borrow_mut().is_err());
+        drop((c, d));
+        assert!(a.try_borrow_mut().is_ok());
+    }
+
+    // `Ref::map_split` must propagate `#[track_caller]` through to the
+    // `BorrowRef::clone()` it calls internally, so the reported location is
+    // the caller's `map_split(...)` call, not the fixed line inside `clone()`.
+    #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+    #[test]
+    fn ref_map_split_location_tracking() {
+        let a = RefCell::new((1, 'b'));
+        let b = a.borrow();
+        let split_line = line!() + 1;
+        let (c, d) = super::Ref::map_split(b, |t| (&t.0, &t.1));
+        let err = match a.try_borrow_mut() {
+            Ok(_) => panic!("expected a borrow conflict"),
+            Err(err) => err,
+        };
+        assert!(err
+            .already_borrowed_at
+            .iter()
+            .any(|loc| loc.to_string().contains(&format!("lib.rs:{split_line}:"))));
+        drop((c, d));
+    }
+
+    #[test]
+    fn ref_mut_map_split() {
+        let a = RefCell::new([1, 2, 3, 4]);
+        let b = a.borrow_mut();
+        let (mut c, mut d) = super::RefMut::map_split(b, |t| t.split_at_mut(2));
+        assert_eq!(*c, [1, 2]);
+        assert_eq!(*d, [3, 4]);
+        assert!(a.try_borrow().is_err());
+        c[0] = 42;
+        d[0] = 24;
+        drop((c, d));
+        assert_eq!(*a.borrow(), [42, 2, 24, 4]);
+    }
+
+    // Same as `ref_map_split_location_tracking` but for the mutable side.
+    #[cfg(any(debug_assertions, feature = "debug_refcell"))]
+    #[test]
+    fn ref_mut_map_split_location_tracking() {
+        let a = RefCell::new([1, 2, 3, 4]);
+        let b = a.borrow_mut();
+        let split_line = line!() + 1;
+        let (c, d) = super::RefMut::map_split(b, |t| t.split_at_mut(2));
+        let err = match a.try_borrow() {
+            Ok(_) => panic!("expected a borrow conflict"),
+            Err(err) => err,
+        };
+        assert!(err
+            .already_borrowed_at
+            .iter()
+            .any(|loc| loc.to_string().contains(&format!("lib.rs:{split_line}:"))));
+        drop((c, d));
+    }
+
+    #[test]
+    fn rwlock_ok_locks() {
+        use super::RwLock;
+
+        let a = RwLock::new(2);
+        let b = a.read();
+        let c = a.read();
+        assert_eq!(*b, 2);
+        assert_eq!(*c, 2);
+        drop((b, c));
+
+        let mut b = a.write();
+        assert_eq!(*b, 2);
+        *b = 4;
+        drop(b);
+
+        assert_eq!(*a.read(), 4);
+    }
+
+    #[test]
+    fn rwlock_across_threads() {
+        use super::RwLock;
+        use std::sync::Arc;
+        use std::thread;
+
+        let a = Arc::new(RwLock::new(0));
+        let mut handles = Vec::new();
+        for i in 0..4 {
+            let a = Arc::clone(&a);
+            handles.push(thread::spawn(move || loop {
+                if let Ok(mut guard) = a.try_write() {
+                    *guard = i;
+                    break;
+                }
+            }));
+        }
+        for handle in handles {
+            handle.join().unwrap();
+        }
+        assert!((0..4).contains(&*a.read()));
+    }
+
+    #[should_panic]
+    #[test]
+    fn rwlock_bad_write_lock() {
+        let a = super::RwLock::new(2);
+        let _a = a.read();
+        a.write();
+    }
+
+    #[should_panic]
+    #[test]
+    fn rwlock_bad_read_lock() {
+        let a = super::RwLock::new(2);
+        let _a = a.write();
+        a.read();
+    }
 }